@@ -1,6 +1,24 @@
+use std::fmt;
 use std::fs;
 use crate::builtin;
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub file_name: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.file_name.as_deref().unwrap_or("<input>");
+        write!(f, "{}:{}:{}: {}", file, self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for LexError {}
+
 #[derive(Debug, PartialEq)]
 pub enum LType {
     LParen, // (
@@ -42,31 +60,75 @@ impl LType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
 #[derive(Debug, PartialEq)]
-pub struct Token(LType);
+pub struct Token {
+    ltype: LType,
+    span: Span,
+}
 
 impl Token {
-    pub fn new(ltype: LType) -> Self {
-        Self ( ltype )
+    pub fn new(ltype: LType, span: Span) -> Self {
+        Self { ltype, span }
+    }
+
+    pub fn ltype(&self) -> &LType {
+        &self.ltype
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
 pub struct Lexer {
-    pub input: String,
+    pub input: Vec<char>,
+    file_name: Option<String>,
     output: Vec<Token>,
     line: usize,
     start: usize,
     current: usize,
+    last_newline: usize,
 }
 
 impl Lexer {
-    pub fn new(file: String) -> Self {
+    pub fn from_str(src: impl Into<String>) -> Self {
         Self {
-            input: fs::read_to_string(file).expect("Cannot read file."),
+            input: src.into().chars().collect(),
+            file_name: None,
             output: vec![],
             line: 1,
             start: 0,
-            current: 0
+            current: 0,
+            last_newline: 0,
+        }
+    }
+
+    pub fn new(file: String) -> Result<Self, LexError> {
+        let src = fs::read_to_string(&file).map_err(|err| LexError {
+            file_name: Some(file.clone()),
+            line: 0,
+            column: 0,
+            message: format!("cannot read file: {err}"),
+        })?;
+        let mut lexer = Self::from_str(src);
+        lexer.file_name = Some(file);
+        Ok(lexer)
+    }
+
+    fn error(&self, message: impl Into<String>) -> LexError {
+        LexError {
+            file_name: self.file_name.clone(),
+            line: self.line,
+            column: self.current - self.last_newline,
+            message: message.into(),
         }
     }
 
@@ -77,24 +139,24 @@ impl Lexer {
             self.line,
             self.start,
             self.current,
-            self.input.chars().nth(self.current)
+            self.input.get(self.current)
         )
     }
 
     pub fn is_eof(&self) -> bool {
-        self.current >= self.input.chars().count()
+        self.current >= self.input.len()
     }
 
     pub fn peek(&self) -> char {
-        self.input.chars().nth(self.current).expect("Hmm.")
+        self.input.get(self.current).copied().unwrap_or('\0')
     }
 
     pub fn advance(&mut self) -> char {
         self.current += 1;
-        self.input.chars().nth(self.current - 1).unwrap()
+        self.input[self.current - 1]
     }
 
-    pub fn lex_one(&mut self) {
+    pub fn lex_one(&mut self) -> Result<(), LexError> {
         let cc = self.advance();
 
         match cc {
@@ -105,78 +167,219 @@ impl Lexer {
             ')' => self.add_token(LType::RParen),
             '"' => {
                     self.start += 1;
-                    self.string();
+                    self.string()?;
                 }
-            '-' => self.add_token(LType::Minus),
+            '-' => if self.peek().is_ascii_digit() {
+                    self.number()?;
+                } else {
+                    self.add_token(LType::Minus);
+                },
             '+' => self.add_token(LType::Plus),
             '*' => self.add_token(LType::Times),
-            '/' => self.add_token(LType::Div),
+            '/' => if self.peek() == '*' {
+                    self.advance();
+                    self.block_comment()?;
+                } else {
+                    self.add_token(LType::Div);
+                },
             '%' => self.add_token(LType::Modulo),
-            '\n' => self.line += 1,
+            ';' => while !self.is_eof() && self.peek() != '\n' { self.advance(); },
+            '\n' => {
+                self.line += 1;
+                self.last_newline = self.current;
+            }
             x => if x.is_numeric() {
-                    self.number();
+                    self.number()?;
                 } else {
-                    self.identifier();
+                    self.identifier()?;
                 }
         }
+
+        Ok(())
+    }
+
+    pub fn block_comment(&mut self) -> Result<(), LexError> {
+        let mut depth = 1;
+
+        while !self.is_eof() && depth > 0 {
+            match self.advance() {
+                '\n' => {
+                    self.line += 1;
+                    self.last_newline = self.current;
+                }
+                '/' if self.peek() == '*' => {
+                    self.advance();
+                    depth += 1;
+                }
+                '*' if self.peek() == '/' => {
+                    self.advance();
+                    depth -= 1;
+                }
+                _ => (),
+            }
+        }
+
+        if depth > 0 {
+            return Err(self.error("unterminated block comment"));
+        }
+
+        Ok(())
     }
 
     pub fn add_token(&mut self, ltype: LType) {
-        self.output.push(Token(ltype));
+        self.add_token_at_line(ltype, self.line, self.last_newline, self.current);
     }
 
-    pub fn string(&mut self) {
-        'outer: while !self.is_eof() {
-            match self.peek() {
-                '"' => break 'outer,
-                '\n' => self.line += 1,
-                _ => (),
+    pub fn add_token_at_line(&mut self, ltype: LType, line: usize, last_newline: usize, column_pos: usize) {
+        let span = Span {
+            start: self.start,
+            end: self.current,
+            line,
+            column: column_pos - last_newline,
+        };
+        self.output.push(Token::new(ltype, span));
+    }
+
+    pub fn string(&mut self) -> Result<(), LexError> {
+        let start_line = self.line;
+        let start_last_newline = self.last_newline;
+        let start_column_pos = self.start;
+        let mut str = String::new();
+
+        loop {
+            if self.is_eof() {
+                return Err(self.error("unterminated string literal"));
             }
-            self.advance();
+
+            match self.advance() {
+                '"' => break,
+                '\n' => {
+                    self.line += 1;
+                    self.last_newline = self.current;
+                    str.push('\n');
+                }
+                '\\' => {
+                    if self.is_eof() {
+                        return Err(self.error("unterminated string literal"));
+                    }
+                    str.push(match self.advance() {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '\\' => '\\',
+                        '"' => '"',
+                        '0' => '\0',
+                        'u' => self.unicode_escape()?,
+                        other => return Err(self.error(format!("unknown escape sequence '\\{other}'"))),
+                    });
+                }
+                c => str.push(c),
+            }
+        }
+
+        self.add_token_at_line(LType::Str(str), start_line, start_last_newline, start_column_pos);
+        Ok(())
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, LexError> {
+        if self.peek() != '{' {
+            return Err(self.error("expected '{' after \\u"));
         }
-        if self.is_eof()  {
-            panic!("Unlimited string.");
+        self.advance();
+
+        let mut digits = String::new();
+        while !self.is_eof() && self.peek() != '}' {
+            digits.push(self.advance());
         }
+        if self.is_eof() {
+            return Err(self.error("unterminated unicode escape"));
+        }
+        self.advance(); // consume '}'
+
+        let code = u32::from_str_radix(&digits, 16)
+            .map_err(|_| self.error(format!("invalid unicode escape '\\u{{{digits}}}'")))?;
+        char::from_u32(code)
+            .ok_or_else(|| self.error(format!("invalid unicode scalar value '\\u{{{digits}}}'")))
+    }
 
-        let str = self.input[self.start..self.current].to_string();
-        self.current += 1; // Spell of quotation marks
-        self.add_token(LType::Str(str));
+    fn is_in_base(c: char, base: u32) -> bool {
+        match base {
+            2 => matches!(c, '0'..='1'),
+            8 => matches!(c, '0'..='7'),
+            16 => c.is_ascii_hexdigit(),
+            _ => c.is_ascii_digit(),
+        }
     }
 
-    pub fn number(&mut self) {
-        let stop = vec![')', '\n', ' '];
+    pub fn number(&mut self) -> Result<(), LexError> {
+        let stop = vec![')', '\n', ' ', ';', '/'];
+
+        // A leading '-' is folded straight into the literal by lex_one (it only
+        // dispatches here when '-' is immediately followed by a digit), so the
+        // sign never becomes its own Minus token and never needs retracting.
+        let negative = self.input[self.start] == '-';
+        if negative {
+            self.advance(); // consume the digit lex_one didn't: the '-' is all it saw
+        }
+        let digit_start = if negative { self.start + 1 } else { self.start };
+
+        let base = if self.input[digit_start] == '0' {
+            match self.peek() {
+                'x' => Some(16),
+                'o' => Some(8),
+                'b' => Some(2),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        if let Some(base) = base {
+            self.advance(); // consume the x/o/b radix marker
+
+            while !self.is_eof() && !stop.contains(&self.peek()) {
+                let c = self.advance();
+                if !Self::is_in_base(c, base) {
+                    return Err(self.error(format!("digit '{c}' is out of range for base {base}")));
+                }
+            }
+
+            let digits: String = self.input[digit_start + 2..self.current].iter().collect();
+            let value = i64::from_str_radix(&digits, base)
+                .map_err(|_| self.error(format!("invalid base-{base} literal '{digits}'")))?;
+            let value = if negative { -value } else { value };
+
+            self.add_token(LType::Integer(value));
+            return Ok(());
+        }
 
         while !self.is_eof() && !stop.contains(&self.peek()) { self.advance(); }
 
-        let num = self.input[self.start..self.current].to_string();
-        
+        // Includes the leading '-' when negative; i64/f64 parsing handles the sign.
+        let num: String = self.input[self.start..self.current].iter().collect();
+
         if num.chars().last().unwrap() == '.' {
-            panic!("Expected a decimal, but nothing found.");
+            return Err(self.error("expected a decimal, but nothing found"));
         }
 
         let value = match num.parse::<i64>() {
             Ok(v) => v as f64,
-            Err(_) => num.parse::<f64>().unwrap()
+            Err(_) => num.parse::<f64>().map_err(|_| self.error(format!("invalid number literal '{num}'")))?
         };
 
-        // Is negative
-        let value = if self.input.chars().nth(self.start - 1).unwrap() == '-' {
-            self.output.pop();
-            -value
-        } else { value };
-
         self.add_token(if value.fract() == 0.0 {
             LType::Integer(value as i64)
         } else {
             LType::Real(value)
         });
+        Ok(())
     }
 
-    pub fn identifier(&mut self) {
-        let stop = vec!['(',')', '\n', ' ', '"'];
+    pub fn identifier(&mut self) -> Result<(), LexError> {
+        let stop = vec!['(',')', '\n', ' ', '"', ';', '/'];
         while !self.is_eof() && !stop.contains(&self.peek()) { self.advance(); }
-        let ident = self.input[self.start..self.current].to_string();
-        
+        let ident: String = self.input[self.start..self.current].iter().collect();
+
         match &ident as &str {
             // Keyword
             "defun" => self.add_token(LType::Defun),
@@ -198,12 +401,38 @@ impl Lexer {
 
             _ => self.add_token(LType::Ident(ident))
         }
+        Ok(())
     }
 
-    pub fn lex(&mut self) {
-        while !self.is_eof() {
-            self.lex_one();
+    pub fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        loop {
+            if self.is_eof() {
+                return Ok(None);
+            }
+
             self.start = self.current;
+            let before = self.output.len();
+            self.lex_one()?;
+
+            if self.output.len() > before {
+                return Ok(self.output.pop());
+            }
+        }
+    }
+
+    pub fn lex(&mut self) -> Result<Vec<Token>, LexError> {
+        self.by_ref().collect()
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_token() {
+            Ok(Some(token)) => Some(Ok(token)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
         }
     }
 }
\ No newline at end of file